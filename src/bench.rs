@@ -1,7 +1,40 @@
 use std::iter::Iterator;
+use std::marker::PhantomData;
+use std::mem;
+use std::ptr;
 use std::time::{Duration, Instant};
 
-use statistics::{max, mean, median, min, quartiles, standard_deviation, variance};
+use libc::{clock_gettime, timespec, CLOCK_PROCESS_CPUTIME_ID};
+
+use statistics::{bootstrap, classify_outliers, max, mean, median, median_abs_dev, min,
+                 quartiles, standard_deviation, variance, Outliers};
+
+/// Number of bootstrap resamples used to estimate the mean/median
+/// confidence intervals in `Summary`.
+const BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// The minimum duration a single measured batch should take. Batches that
+/// run faster than this are doubled so that per-call timer overhead (and
+/// noise from the scheduler) is amortized away.
+const BENCH_BATCH_MIN_DUR: Duration = Duration::from_millis(1);
+
+/// Default minimum number of samples `iter` collects when the caller
+/// configures neither `bench_min_dur` nor `bench_min_iters` (mirroring
+/// libtest's default of collecting around this many samples).
+const BENCH_DEFAULT_MIN_SAMPLES: u64 = 50;
+
+/// An identity function that hints to the optimizer that its argument is
+/// used. Wrap the return value (and any otherwise-unused inputs) of the
+/// code under measurement in `black_box` so the compiler can't prove the
+/// work is dead and elide it.
+#[inline(never)]
+pub fn black_box<T>(dummy: T) -> T {
+    unsafe {
+        let ret = ptr::read_volatile(&dummy);
+        mem::forget(dummy);
+        ret
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
 enum BencherState {
@@ -10,8 +43,92 @@ enum BencherState {
     Abort,
 }
 
+/// A pluggable timer backend. `Bencher` uses `M::start`/`M::end` to take
+/// the sample it records into `durations`, so swapping `M` changes what a
+/// benchmark actually measures (wall-clock time, CPU time, ...) without
+/// touching the calibration loop in `iter`.
+///
+/// `Measurement: Clone` so that `Bencher<M>`/`BenchmarkGroup<M>` (both of
+/// which only ever hold `M` in a `PhantomData`) can keep their derived
+/// `Clone` impls without every builder method needing an explicit
+/// `M: Clone` bound.
+pub trait Measurement: Clone {
+    /// Opaque reading taken at the start of a measured batch.
+    type Intermediate;
+    /// The elapsed reading produced by `end`.
+    type Value: Copy;
+
+    fn start() -> Self::Intermediate;
+    fn end(start: Self::Intermediate) -> Self::Value;
+    fn to_f64(value: Self::Value) -> f64;
+}
+
+/// Wall-clock time via `std::time::Instant`. The default measurement, and
+/// the behavior `Bencher` always had before `Measurement` existed.
+#[derive(Debug, Clone, Copy)]
+pub struct WallTime;
+
+impl Measurement for WallTime {
+    type Intermediate = Instant;
+    type Value = Duration;
+
+    fn start() -> Instant {
+        Instant::now()
+    }
+
+    fn end(start: Instant) -> Duration {
+        start.elapsed()
+    }
+
+    fn to_f64(value: Duration) -> f64 {
+        value.as_secs() as f64 + value.subsec_nanos() as f64 / 1e9
+    }
+}
+
+/// Process CPU time via `clock_gettime(CLOCK_PROCESS_CPUTIME_ID)`, so a
+/// benchmark's timing excludes time the process spent descheduled.
+#[derive(Debug, Clone, Copy)]
+pub struct CpuTime;
+
+impl CpuTime {
+    fn now() -> timespec {
+        let mut ts = timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        unsafe {
+            clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut ts);
+        }
+        ts
+    }
+}
+
+impl Measurement for CpuTime {
+    type Intermediate = timespec;
+    type Value = Duration;
+
+    fn start() -> timespec {
+        CpuTime::now()
+    }
+
+    fn end(start: timespec) -> Duration {
+        let end = CpuTime::now();
+        let secs = end.tv_sec - start.tv_sec;
+        let nanos = end.tv_nsec - start.tv_nsec;
+        if nanos >= 0 {
+            Duration::new(secs as u64, nanos as u32)
+        } else {
+            Duration::new((secs - 1) as u64, (nanos + 1_000_000_000) as u32)
+        }
+    }
+
+    fn to_f64(value: Duration) -> f64 {
+        value.as_secs() as f64 + value.subsec_nanos() as f64 / 1e9
+    }
+}
+
 #[derive(Debug, Clone)]
-pub struct Bencher {
+pub struct Bencher<M: Measurement = WallTime> {
     name: String,
     durations: Vec<Duration>,
     bench_min_dur: Option<Duration>,
@@ -22,6 +139,8 @@ pub struct Bencher {
     state: BencherState,
     cpu_time: Duration,
     wall_time: Duration,
+    bytes: u64,
+    _measurement: PhantomData<M>,
 }
 
 fn display(d: &Duration) -> String {
@@ -29,8 +148,15 @@ fn display(d: &Duration) -> String {
     format!("{:.2}s", s)
 }
 
-impl Bencher {
-    pub fn default(name: &str) -> Bencher {
+/// Build a `Duration` from a (possibly fractional) number of seconds.
+fn duration_from_secs(secs: f64) -> Duration {
+    let whole = secs.trunc();
+    let nanos = ((secs - whole) * 1e9) as u32;
+    Duration::new(whole as u64, nanos)
+}
+
+impl<M: Measurement> Bencher<M> {
+    pub fn default(name: &str) -> Bencher<M> {
         Bencher {
             name: name.to_owned(),
             durations: vec![],
@@ -42,34 +168,44 @@ impl Bencher {
             independent_variable: None,
             cpu_time: Duration::new(0,0),
             wall_time: Duration::new(0,0),
+            bytes: 0,
+            _measurement: PhantomData,
         }
     }
 
+    /// Record that each iteration processes `n` bytes, so `summary()` can
+    /// report a throughput (bytes/sec, shown as MB/s by
+    /// `Summary::display`) alongside the timing.
+    pub fn bytes(&mut self, n: u64) -> Bencher<M> {
+        self.bytes = n;
+        self.clone()
+    }
+
     /// Warmup for at least `secs` seconds.
-    pub fn warmup_secs(&mut self, secs: u64) -> Bencher {
+    pub fn warmup_secs(&mut self, secs: u64) -> Bencher<M> {
         self.warmup_dur = Some(Duration::from_secs(secs));
         self.clone()
     }
 
     /// Warmup for at least `i` runs.
-    pub fn warmup_iters(&mut self, i: u64) -> Bencher {
+    pub fn warmup_iters(&mut self, i: u64) -> Bencher<M> {
         self.warmup_iters = Some(i);
         self.clone()
     }
 
     /// Bench for at least `secs` seconds.
-    pub fn bench_min_secs(&mut self, secs: u64) -> Bencher {
+    pub fn bench_min_secs(&mut self, secs: u64) -> Bencher<M> {
         self.bench_min_dur = Some(Duration::from_secs(secs));
         self.clone()
     }
 
     /// Bench for at least `i` runs.
-    pub fn bench_min_iters(&mut self, i: u64) -> Bencher {
+    pub fn bench_min_iters(&mut self, i: u64) -> Bencher<M> {
         self.bench_min_iters = Some(i);
         self.clone()
     }
 
-    pub fn independent_variable(&mut self, u: u64) -> Bencher {
+    pub fn independent_variable(&mut self, u: u64) -> Bencher<M> {
         self.independent_variable = Some(u);
         self.clone()
     }
@@ -94,7 +230,7 @@ impl Bencher {
 
     pub fn abort_or_run<F>(&mut self, f: &F)
     where
-        F: Fn(&mut Bencher),
+        F: Fn(&mut Bencher<M>),
     {
         match self.state.clone() {
             BencherState::Abort => panic!("Aborting benchmark!"),
@@ -104,7 +240,7 @@ impl Bencher {
 
     pub fn run_manual<F>(&mut self, f: F)
     where
-        F: Fn(&mut Bencher),
+        F: Fn(&mut Bencher<M>),
     {
         let mut num_warmups = 0;
         // run warmup for at least so long
@@ -148,11 +284,71 @@ impl Bencher {
         }
     }
 
-    pub fn iter<F>(&mut self, f: F)
+    /// Run `f` repeatedly, self-calibrating the number of inner repetitions
+    /// per sample so that per-call timer overhead is negligible, and push
+    /// `elapsed / n` samples into `durations` until `bench_min_dur` and
+    /// `bench_min_iters` (if set) are satisfied. If neither is set, collect
+    /// `BENCH_DEFAULT_MIN_SAMPLES` samples instead of stopping after one.
+    pub fn iter<T, F>(&mut self, mut f: F)
     where
-        F: Fn(),
+        F: FnMut() -> T,
     {
-        unimplemented!();
+        self.state = BencherState::Bench;
+
+        let target_secs = BENCH_BATCH_MIN_DUR.as_secs() as f64
+            + BENCH_BATCH_MIN_DUR.subsec_nanos() as f64 / 1e9;
+
+        // Time a single call to estimate how many inner repetitions fit in
+        // one `BENCH_BATCH_MIN_DUR` window.
+        let one_secs = M::to_f64(self.time_batch(1, &mut f));
+        let mut n: u64 = ((target_secs / one_secs.max(1e-12)) as u64 + 1).next_power_of_two();
+
+        let bench_start = Instant::now();
+        let mut num_samples = 0u64;
+        loop {
+            let batch_secs = M::to_f64(self.time_batch(n, &mut f));
+            if batch_secs < target_secs {
+                n *= 2;
+                continue;
+            }
+
+            self.manual_dur(Some(duration_from_secs(batch_secs / n as f64)));
+            num_samples += 1;
+
+            let min_dur_met = self.bench_min_dur
+                .map_or(true, |min| bench_start.elapsed() >= min);
+            let min_iters_met = match self.bench_min_iters {
+                Some(min) => num_samples >= min,
+                // Neither bound was configured: fall back to a sensible
+                // default sample count instead of stopping after one.
+                None if self.bench_min_dur.is_none() => num_samples >= BENCH_DEFAULT_MIN_SAMPLES,
+                None => true,
+            };
+            if min_dur_met && min_iters_met {
+                break;
+            }
+        }
+    }
+
+    /// Run `f` `n` times in a tight loop, feeding its result through
+    /// `black_box` so the optimizer can't eliminate the work, and return
+    /// the elapsed reading from the active measurement `M`. Also
+    /// accumulates the batch's wall-clock and CPU time into `wall_time`
+    /// and `cpu_time`, regardless of which measurement `M` is selected.
+    fn time_batch<T, F>(&mut self, n: u64, f: &mut F) -> M::Value
+    where
+        F: FnMut() -> T,
+    {
+        let wall_start = WallTime::start();
+        let cpu_start = CpuTime::start();
+        let m_start = M::start();
+        for _ in 0..n {
+            black_box(f());
+        }
+        let value = M::end(m_start);
+        self.wall_time += WallTime::end(wall_start);
+        self.cpu_time += CpuTime::end(cpu_start);
+        value
     }
 
     pub fn summary(&self) -> Summary {
@@ -166,24 +362,51 @@ impl Bencher {
             let (q1, _, q3) = quartiles;
             q3 - q1
         };
+        let median = median(&secs).unwrap_or_else(|| 0.0);
+        let bytes_per_sec = if self.bytes > 0 && median > 0.0 {
+            self.bytes as f64 / median
+        } else {
+            0.0
+        };
+        let outliers = classify_outliers(&secs, quartiles);
+        let mean_ci = bootstrap(&secs, BOOTSTRAP_RESAMPLES, |d| mean(d).unwrap_or(0.0));
+        let median_ci = bootstrap(&secs, BOOTSTRAP_RESAMPLES, |d| median(d).unwrap_or(0.0));
+        let mean = mean(&secs).unwrap_or_else(|| 0.0);
+        let std_dev = standard_deviation(&secs, None).unwrap_or_else(|| 0.0);
+        let std_dev_pct = if mean > 0.0 { std_dev / mean * 100.0 } else { 0.0 };
+        let median_abs_dev = median_abs_dev(&secs).unwrap_or_else(|| 0.0);
+        let median_abs_dev_pct = if median > 0.0 {
+            median_abs_dev / median * 100.0
+        } else {
+            0.0
+        };
 
         Summary {
             name: self.name.clone(),
             n: secs.len() as u64,
-            mean: mean(&secs).unwrap_or_else(|| 0.0),
+            mean: mean,
             min: min(&secs).unwrap_or_else(|| 0.0),
             max: max(&secs).unwrap_or_else(|| 0.0),
-            median: median(&secs).unwrap_or_else(|| 0.0),
+            median: median,
             quartiles: quartiles,
             iqr: iqr,
             var: variance(&secs, None).unwrap_or_else(|| 0.0),
-            std_dev: standard_deviation(&secs, None).unwrap_or_else(|| 0.0),
+            std_dev: std_dev,
+            std_dev_pct: std_dev_pct,
+            median_abs_dev: median_abs_dev,
+            median_abs_dev_pct: median_abs_dev_pct,
             independent_variable: self.independent_variable,
+            bytes_per_sec: bytes_per_sec,
+            outliers: outliers,
+            mean_ci: mean_ci,
+            median_ci: median_ci,
+            wall_time: WallTime::to_f64(self.wall_time),
+            cpu_time: WallTime::to_f64(self.cpu_time),
         }
     }
 }
 
-impl Iterator for Bencher {
+impl<M: Measurement> Iterator for Bencher<M> {
     type Item = i32;
 
     // Here, we define the sequence using `.curr` and `.next`.
@@ -213,13 +436,105 @@ pub struct Summary {
     median: f64,
     var: f64,
     std_dev: f64,
-    // std_dev_pct: f64,
-    // median_abs_dev: f64,
-    // median_abs_dev_pct: f64,
+    /// `std_dev` as a percentage of `mean`.
+    std_dev_pct: f64,
+    /// Median absolute deviation, scaled to estimate `std_dev` but far
+    /// less sensitive to outliers.
+    median_abs_dev: f64,
+    /// `median_abs_dev` as a percentage of `median`.
+    median_abs_dev_pct: f64,
     quartiles: (f64, f64, f64),
     // /// Interquartile Range
     iqr: f64,
     independent_variable: Option<u64>,
+    /// Throughput in bytes/sec, computed from `Bencher::bytes` and the
+    /// median sample duration. Zero when no `bytes` were recorded.
+    bytes_per_sec: f64,
+    /// Samples falling outside the Tukey fences built from `quartiles`.
+    outliers: Outliers,
+    /// 95% bootstrap confidence interval for the mean, as `(lower, point, upper)`.
+    mean_ci: Option<(f64, f64, f64)>,
+    /// 95% bootstrap confidence interval for the median, as `(lower, point, upper)`.
+    median_ci: Option<(f64, f64, f64)>,
+    /// Total wall-clock time, in seconds, spent running `iter` batches.
+    wall_time: f64,
+    /// Total process CPU time, in seconds, spent running `iter` batches.
+    cpu_time: f64,
+}
+
+impl Summary {
+    /// Render this summary the way libtest prints its bench samples:
+    /// `X ns/iter (+/- Y)`, followed by the median's bootstrap confidence
+    /// interval as ` [lo .. hi]` (in ns) when there is one, and a trailing
+    /// `= Z MB/s` when a throughput was recorded via `Bencher::bytes`.
+    pub fn display(&self) -> String {
+        let ns_iter = self.median * 1e9;
+        let ns_dev = self.std_dev * 1e9;
+        let mut s = format!("{:>11} ns/iter (+/- {})", ns_iter as u64, ns_dev as u64);
+        if let Some((lo, _, hi)) = self.median_ci {
+            s.push_str(&format!(" [{:.0} .. {:.0}]", lo * 1e9, hi * 1e9));
+        }
+        if self.bytes_per_sec > 0.0 {
+            let mb_s = self.bytes_per_sec / (1024.0 * 1024.0);
+            s.push_str(&format!(" = {:.2} MB/s", mb_s));
+        }
+        s
+    }
+}
+
+/// A group of parameterized benchmarks, run once per value of an
+/// independent variable (e.g. input size) and collected into one
+/// serialized record so a scaling curve (time vs. input size) can be
+/// plotted directly from the output.
+#[derive(Debug, Clone)]
+pub struct BenchmarkGroup<M: Measurement = WallTime> {
+    name: String,
+    values: Vec<u64>,
+    _measurement: PhantomData<M>,
+}
+
+impl<M: Measurement> BenchmarkGroup<M> {
+    pub fn new(name: &str) -> BenchmarkGroup<M> {
+        BenchmarkGroup {
+            name: name.to_owned(),
+            values: vec![],
+            _measurement: PhantomData,
+        }
+    }
+
+    /// Parameter values the routine will be run against, once each.
+    pub fn values(&mut self, values: &[u64]) -> BenchmarkGroup<M> {
+        self.values = values.to_vec();
+        self.clone()
+    }
+
+    /// Run `f` once per configured value, setting each `Bencher`'s
+    /// `independent_variable` to that value before calling `f`, and
+    /// collect the resulting summaries into one `GroupSummary`.
+    pub fn run<F>(&self, mut f: F) -> GroupSummary
+    where
+        F: FnMut(&mut Bencher<M>, u64),
+    {
+        let summaries = self.values
+            .iter()
+            .map(|&v| {
+                let mut b = Bencher::<M>::default(&self.name).independent_variable(v);
+                f(&mut b, v);
+                b.summary()
+            })
+            .collect();
+
+        GroupSummary {
+            name: self.name.clone(),
+            summaries: summaries,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupSummary {
+    name: String,
+    summaries: Vec<Summary>,
 }
 
 /*
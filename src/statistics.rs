@@ -129,6 +129,135 @@ pub fn standard_deviation<T>(v: &[T], vbar: Option<T>) -> Option<T>
     }
 }
 
+/// A small, seedable xorshift64 PRNG used for reproducible bootstrap
+/// resampling.
+struct Xorshift64 {
+    state: u64,
+}
+
+impl Xorshift64 {
+    fn new(seed: u64) -> Xorshift64 {
+        Xorshift64 {
+            state: if seed == 0 { 0xdead_beef } else { seed },
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    /// A uniform index in `[0, bound)`.
+    fn next_index(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+}
+
+/// Default seed for `bootstrap`, chosen so repeated runs over the same
+/// data produce the same confidence interval.
+const BOOTSTRAP_SEED: u64 = 0x5eed_1234_dead_beef;
+
+/// Estimate a 95% confidence interval for `stat(data)` by bootstrap
+/// resampling: draw `n = data.len()` samples with replacement `resamples`
+/// times, compute `stat` on each resample, and take the 2.5th/97.5th
+/// percentiles of the resulting distribution. Returns
+/// `(lower, point, upper)`, or `None` if `data` is empty or `resamples`
+/// is 0.
+pub fn bootstrap<F>(data: &[f64], resamples: usize, stat: F) -> Option<(f64, f64, f64)>
+where
+    F: Fn(&[f64]) -> f64,
+{
+    if data.len() == 0 || resamples == 0 {
+        return None;
+    }
+
+    let point = stat(data);
+    let mut rng = Xorshift64::new(BOOTSTRAP_SEED);
+    let mut resample = vec![0.0; data.len()];
+    let mut estimates: Vec<f64> = (0..resamples)
+        .map(|_| {
+            for slot in resample.iter_mut() {
+                *slot = data[rng.next_index(data.len())];
+            }
+            stat(&resample)
+        })
+        .collect();
+    estimates.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Less));
+
+    let lower_i = (resamples as f64 * 0.025) as usize;
+    let upper_i = ((resamples as f64 * 0.975) as usize).min(resamples - 1);
+
+    Some((estimates[lower_i], point, estimates[upper_i]))
+}
+
+/// Scales the median absolute deviation so it estimates the standard
+/// deviation of a normally distributed sample.
+const MAD_CONSISTENCY_CONSTANT: f64 = 1.4826;
+
+/// Median absolute deviation: `1.4826 * median(|x_i - median(x)|)`. A
+/// robust dispersion estimate, far less sensitive to outliers than
+/// `standard_deviation`.
+pub fn median_abs_dev(v: &[f64]) -> Option<f64> {
+    match median(v) {
+        Some(x) => {
+            let abs_devs: Vec<f64> = v.iter().map(|xi| (xi - x).abs()).collect();
+            median(&abs_devs).map(|mad| mad * MAD_CONSISTENCY_CONSTANT)
+        }
+        None => None,
+    }
+}
+
+/// Counts of samples falling outside the Tukey fences built from a
+/// distribution's quartiles: `mild` fences are `1.5 * IQR` beyond Q1/Q3,
+/// `severe` fences are `3 * IQR` beyond them.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Outliers {
+    pub low_severe: u64,
+    pub low_mild: u64,
+    pub high_mild: u64,
+    pub high_severe: u64,
+}
+
+impl Outliers {
+    pub fn total(&self) -> u64 {
+        self.low_severe + self.low_mild + self.high_mild + self.high_severe
+    }
+}
+
+/// Classify each sample in `t` against the Tukey fences built from
+/// `quartiles` (as returned by `quartiles(t)`).
+pub fn classify_outliers<T>(t: &[T], quartiles: (T, T, T)) -> Outliers
+where
+    T: Float,
+{
+    let (q1, _, q3) = quartiles;
+    let iqr = q3 - q1;
+    let mild: T = cast(1.5).unwrap();
+    let severe: T = cast(3.0).unwrap();
+    let low_mild_fence = q1 - iqr * mild;
+    let low_severe_fence = q1 - iqr * severe;
+    let high_mild_fence = q3 + iqr * mild;
+    let high_severe_fence = q3 + iqr * severe;
+
+    let mut outliers = Outliers::default();
+    for &x in t {
+        if x < low_severe_fence {
+            outliers.low_severe += 1;
+        } else if x < low_mild_fence {
+            outliers.low_mild += 1;
+        } else if x > high_severe_fence {
+            outliers.high_severe += 1;
+        } else if x > high_mild_fence {
+            outliers.high_mild += 1;
+        }
+    }
+    outliers
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,4 +275,31 @@ mod tests {
         assert_eq!(quartiles(&vec![1, 2, 3, 4]), Some((1, 2, 3)));
         assert_eq!(quartiles(&vec![1, 2, 3, 4, 5]), Some((1, 3, 4)));
     }
+
+    #[test]
+    fn classify_outliers_test() {
+        let data = vec![1.0, 2.0, 2.0, 3.0, 3.0, 3.0, 4.0, 4.0, 5.0, 100.0];
+        let q = quartiles(&data).unwrap();
+        let outliers = classify_outliers(&data, q);
+        assert_eq!(outliers.high_severe, 1);
+        assert_eq!(outliers.total(), 1);
+    }
+
+    #[test]
+    fn bootstrap_test() {
+        assert!(bootstrap(&[], 1000, |d| mean(d).unwrap()).is_none());
+        assert!(bootstrap(&[1.0, 2.0, 3.0], 0, |d| mean(d).unwrap()).is_none());
+
+        let data = vec![1.0, 2.0, 3.0, 4.0, 5.0];
+        let (lower, point, upper) = bootstrap(&data, 1000, |d| mean(d).unwrap()).unwrap();
+        assert_eq!(point, 3.0);
+        assert!(lower <= point);
+        assert!(point <= upper);
+    }
+
+    #[test]
+    fn median_abs_dev_test() {
+        assert!(median_abs_dev(&vec![]).is_none());
+        assert_eq!(median_abs_dev(&vec![1.0, 2.0, 3.0, 4.0, 5.0]), Some(1.0 * 1.4826));
+    }
 }